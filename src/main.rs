@@ -1,15 +1,226 @@
+use std::fmt;
 use std::fs::{self, OpenOptions};
 use std::io::{Read, Seek, SeekFrom, Write};
 use std::path::{Component, Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
 
 use clap::Parser;
 use fatfs::{FileSystem, FsOptions};
+use fscommon::StreamSlice;
 use serde::Deserialize;
 
 // Custom trait that combines Read, Write, and Seek
 trait ReadWriteSeek: Read + Write + Seek {}
 impl<T: Read + Write + Seek> ReadWriteSeek for T {}
 
+/// Crate error type: every variant that wraps a failed I/O (or parse) operation keeps
+/// that underlying error behind `source()` (the pure validation variants have none),
+/// so callers can inspect it programmatically instead of matching on
+/// formatted text, while `main` still prints the full "what failed, because what"
+/// cause chain a single pre-joined `String` would have thrown away.
+#[derive(Debug)]
+enum MkfatError {
+    ReadManifest { path: PathBuf, source: std::io::Error },
+    ReadManifestStdin(std::io::Error),
+    ParseManifest(serde_json::Error),
+    MissingOutput,
+    ReadInput { path: PathBuf, source: std::io::Error },
+    ReadDir { path: PathBuf, source: std::io::Error },
+    Stat { path: PathBuf, source: std::io::Error },
+    OpenOutput { path: PathBuf, source: std::io::Error },
+    SetImageSize(std::io::Error),
+    WriteMbr { detail: &'static str, source: std::io::Error },
+    Format(std::io::Error),
+    CreateFilesystem(std::io::Error),
+    CreateDir { path: String, source: std::io::Error },
+    CreateFile { name: String, source: std::io::Error },
+    WriteFile { name: String, source: std::io::Error },
+    Seek(std::io::Error),
+    InvalidName { path: String, component: String, reason: NameIssue },
+    InvalidPath(String),
+    Reopen { path: PathBuf, source: std::io::Error },
+    Stats(std::io::Error),
+    VariantMismatch { requested: fatfs::FatType, actual: fatfs::FatType },
+    CurrentDir(std::io::Error),
+    TimestampTooOld(i64),
+}
+
+impl fmt::Display for MkfatError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            MkfatError::ReadManifest { path, .. } => {
+                write!(f, "failed to read manifest file '{}'", path.display())
+            }
+            MkfatError::ReadManifestStdin(_) => write!(f, "failed to read manifest from stdin"),
+            MkfatError::ParseManifest(_) => write!(f, "failed to parse manifest JSON"),
+            MkfatError::MissingOutput => write!(
+                f,
+                "output path not specified; provide --output or 'out' in the manifest"
+            ),
+            MkfatError::ReadInput { path, .. } => {
+                write!(f, "failed to read input file '{}'", path.display())
+            }
+            MkfatError::ReadDir { path, .. } => {
+                write!(f, "failed to read directory '{}'", path.display())
+            }
+            MkfatError::Stat { path, .. } => write!(f, "failed to stat '{}'", path.display()),
+            MkfatError::OpenOutput { path, .. } => {
+                write!(f, "failed to open output file '{}'", path.display())
+            }
+            MkfatError::SetImageSize(_) => write!(f, "failed to set image size"),
+            MkfatError::WriteMbr { detail, .. } => write!(f, "failed to write MBR {}", detail),
+            MkfatError::Format(_) => write!(f, "failed to format volume"),
+            MkfatError::CreateFilesystem(_) => write!(f, "failed to open formatted filesystem"),
+            MkfatError::CreateDir { path, .. } => {
+                write!(f, "failed to create directory '{}'", path)
+            }
+            MkfatError::CreateFile { name, .. } => write!(f, "failed to create file '{}'", name),
+            MkfatError::WriteFile { name, .. } => write!(f, "failed to write file '{}'", name),
+            MkfatError::Seek(_) => write!(f, "failed to seek in image file"),
+            MkfatError::InvalidName { path, component, reason } => write!(
+                f,
+                "invalid name '{}' in output path '{}': {}",
+                component, path, reason
+            ),
+            MkfatError::InvalidPath(detail) => write!(f, "{}", detail),
+            MkfatError::Reopen { path, .. } => write!(
+                f,
+                "failed to reopen output file '{}' for verification",
+                path.display()
+            ),
+            MkfatError::Stats(_) => write!(f, "failed to read filesystem stats"),
+            MkfatError::VariantMismatch { requested, actual } => write!(
+                f,
+                "requested FAT type {:?} but the formatter produced {:?}; the image's size \
+                 doesn't fit the requested variant",
+                requested, actual
+            ),
+            MkfatError::CurrentDir(_) => write!(f, "failed to get current directory"),
+            MkfatError::TimestampTooOld(ts) => write!(
+                f,
+                "timestamp {} is before 1980-01-01, the earliest date FAT can represent",
+                ts
+            ),
+        }
+    }
+}
+
+impl std::error::Error for MkfatError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            MkfatError::ReadManifest { source, .. }
+            | MkfatError::ReadInput { source, .. }
+            | MkfatError::ReadDir { source, .. }
+            | MkfatError::Stat { source, .. }
+            | MkfatError::OpenOutput { source, .. }
+            | MkfatError::SetImageSize(source)
+            | MkfatError::WriteMbr { source, .. }
+            | MkfatError::Format(source)
+            | MkfatError::CreateFilesystem(source)
+            | MkfatError::CreateDir { source, .. }
+            | MkfatError::CreateFile { source, .. }
+            | MkfatError::WriteFile { source, .. }
+            | MkfatError::Seek(source)
+            | MkfatError::Reopen { source, .. }
+            | MkfatError::Stats(source)
+            | MkfatError::ReadManifestStdin(source)
+            | MkfatError::CurrentDir(source) => Some(source),
+            MkfatError::ParseManifest(source) => Some(source),
+            MkfatError::MissingOutput
+            | MkfatError::InvalidName { .. }
+            | MkfatError::InvalidPath(_)
+            | MkfatError::VariantMismatch { .. }
+            | MkfatError::TimestampTooOld(_) => None,
+        }
+    }
+}
+
+/// Wraps a `fatfs` error (whose concrete type varies with the backing I/O) in a plain
+/// `std::io::Error` so it can be carried as a typed `MkfatError` source.
+fn fatfs_io_error(e: impl std::fmt::Display) -> std::io::Error {
+    std::io::Error::other(e.to_string())
+}
+
+const SECTOR_SIZE: u64 = 512;
+const DEFAULT_PARTITION_START_LBA: u32 = 2048;
+
+/// Encodes an LBA as a 3-byte CHS address, clamping to `0xFE 0xFF 0xFF` once it exceeds
+/// the classic CHS range, as every modern LBA-addressed partition does.
+fn chs_bytes(lba: u32) -> [u8; 3] {
+    const MAX_HEADS: u32 = 255;
+    const MAX_SECTORS: u32 = 63;
+    const MAX_CYLINDER: u32 = 1023;
+
+    let cylinder = lba / (MAX_HEADS * MAX_SECTORS);
+    if cylinder > MAX_CYLINDER {
+        return [0xFE, 0xFF, 0xFF];
+    }
+    let head = (lba / MAX_SECTORS) % MAX_HEADS;
+    let sector = (lba % MAX_SECTORS) + 1;
+    [head as u8, (sector as u8) | (((cylinder >> 8) as u8) << 6), (cylinder & 0xFF) as u8]
+}
+
+/// Writes a single-partition MBR at LBA 0: a 16-byte partition entry at offset 446 and
+/// the `0x55AA` boot signature at offset 510, so the image can be `dd`'d straight to a
+/// disk or used as a VM drive.
+fn write_mbr<W: Write + Seek>(
+    w: &mut W,
+    fat_type: FatType,
+    start_lba: u32,
+    total_sectors: u32,
+) -> Result<(), MkfatError> {
+    let partition_sectors = total_sectors.saturating_sub(start_lba);
+    let partition_type = match fat_type {
+        FatType::Fat12 => 0x01,
+        FatType::Fat16 => 0x0E,
+        FatType::Fat32 => 0x0C,
+    };
+
+    let mut entry = [0u8; 16];
+    entry[0] = 0x80; // boot flag: active partition
+    entry[1..4].copy_from_slice(&chs_bytes(start_lba));
+    entry[4] = partition_type;
+    entry[5..8].copy_from_slice(&chs_bytes(start_lba + partition_sectors.saturating_sub(1)));
+    entry[8..12].copy_from_slice(&start_lba.to_le_bytes());
+    entry[12..16].copy_from_slice(&partition_sectors.to_le_bytes());
+
+    w.seek(SeekFrom::Start(446))
+        .map_err(|e| MkfatError::WriteMbr { detail: "(seek to partition table)", source: e })?;
+    w.write_all(&entry)
+        .map_err(|e| MkfatError::WriteMbr { detail: "partition entry", source: e })?;
+
+    w.seek(SeekFrom::Start(510))
+        .map_err(|e| MkfatError::WriteMbr { detail: "(seek to signature)", source: e })?;
+    w.write_all(&[0x55, 0xAA])
+        .map_err(|e| MkfatError::WriteMbr { detail: "signature", source: e })?;
+
+    Ok(())
+}
+
+/// Wraps `inner` in a `StreamSlice` bounded to `[start_lba*SECTOR_SIZE, image_bytes)`,
+/// the partition's byte range within the image. `StreamSlice::new` asserts its bounds
+/// are well-formed, which would otherwise panic the process on a partition start past
+/// the end of a too-small image instead of reporting it as a regular `MkfatError`.
+fn partition_stream_slice(
+    inner: Box<dyn ReadWriteSeek>,
+    start_lba: u32,
+    image_bytes: u64,
+) -> Result<Box<dyn ReadWriteSeek>, MkfatError> {
+    let start_offset = start_lba as u64 * SECTOR_SIZE;
+    if start_offset > image_bytes {
+        return Err(MkfatError::Seek(std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            format!(
+                "partition start (LBA {start_lba}, byte {start_offset}) is past the end \
+                 of the {image_bytes}-byte image"
+            ),
+        )));
+    }
+    Ok(Box::new(
+        StreamSlice::new(inner, start_offset, image_bytes).map_err(MkfatError::Seek)?,
+    ))
+}
+
 #[derive(Parser, Debug)]
 #[command(name = "mkfat")]
 #[command(about = "Create a FAT filesystem from a JSON description.")]
@@ -26,9 +237,10 @@ struct Cli {
     #[arg(short, long)]
     output: Option<PathBuf>,
 
-    /// Size of the image in MB
-    #[arg(short = 's', long, default_value_t = 16)]
-    size_mb: u64,
+    /// Size of the image in MB, or `auto` to size it to exactly fit the manifest
+    /// (plus a heuristic allowance for FAT/directory-entry overhead).
+    #[arg(short = 's', long, default_value = "16")]
+    size_mb: SizeArg,
 
     /// Set the volume label
     #[arg(short, long, default_value = "FATFS")]
@@ -45,6 +257,73 @@ struct Cli {
     /// Quiet output
     #[arg(short, long)]
     quiet: bool,
+
+    /// Unix timestamp (seconds since epoch) to stamp every file and directory with, for
+    /// byte-reproducible images. Defaults to `SOURCE_DATE_EPOCH` if set; otherwise each
+    /// file keeps its own source mtime and directories are stamped with the current time.
+    #[arg(long)]
+    timestamp: Option<i64>,
+
+    /// Wrap the FAT volume in an MBR partition table (starting at `--partition-start-lba`)
+    /// so the image can be written straight to a disk or used as a VM drive.
+    #[arg(long)]
+    partitioned: bool,
+
+    /// Starting LBA of the partition when `--partitioned` (or a manifest `partition` object)
+    /// is in effect. Defaults to 2048 (1 MiB alignment).
+    #[arg(long)]
+    partition_start_lba: Option<u32>,
+
+    /// How to handle entry names that violate VFAT/LFN constraints: fail the build,
+    /// drop the entry, or rewrite it deterministically.
+    #[arg(long, value_enum, default_value = "error")]
+    on_invalid: OnInvalidPolicy,
+
+    /// Reopen the formatted volume after writing and report its geometry (FAT type,
+    /// cluster size, space used/free, per-file sizes); fails if the formatter silently
+    /// chose a different FAT type than the one requested.
+    #[arg(long)]
+    verify: bool,
+
+    /// Bytes per sector for the formatted volume (overrides manifest build_args).
+    #[arg(long)]
+    bytes_per_sector: Option<u16>,
+
+    /// Bytes per cluster for the formatted volume (overrides manifest build_args);
+    /// also the rounding unit used by `--size-mb auto`. When unset, fatfs picks a
+    /// size-adaptive cluster size and `--size-mb auto` rounds to 4096.
+    #[arg(long)]
+    bytes_per_cluster: Option<u32>,
+}
+
+/// Either an explicit image size in MB, or `auto` to size the image to exactly fit the
+/// manifest's files.
+#[derive(Debug, Clone)]
+enum SizeArg {
+    Mb(u64),
+    Auto,
+}
+
+impl std::str::FromStr for SizeArg {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.eq_ignore_ascii_case("auto") {
+            Ok(SizeArg::Auto)
+        } else {
+            s.parse::<u64>()
+                .map(SizeArg::Mb)
+                .map_err(|_| format!("invalid size '{}': expected a number of MB or 'auto'", s))
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, clap::ValueEnum)]
+#[value(rename_all = "kebab-case")]
+enum OnInvalidPolicy {
+    Error,
+    Skip,
+    Sanitize,
 }
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, clap::ValueEnum)]
@@ -130,6 +409,402 @@ impl<'de> Deserialize<'de> for FileEntry {
     }
 }
 
+#[derive(Debug)]
+struct DirEntry {
+    in_dir: String,
+    out: Option<String>,
+    /// `*`/`?` glob patterns (relative to `in_dir`) for entries to skip during the walk.
+    exclude: Vec<String>,
+}
+
+impl DirEntry {
+    fn get_in_dir(&self) -> &str {
+        &self.in_dir
+    }
+
+    fn get_out(&self) -> &str {
+        self.out.as_deref().unwrap_or_else(|| self.get_in_dir())
+    }
+}
+
+/// A `files` manifest entry: either a single file (`FileEntry`) or, when the object uses
+/// `in_dir` instead of `in`, a directory tree to mirror recursively (`DirEntry`).
+#[derive(Debug)]
+enum ManifestEntry {
+    File(FileEntry),
+    Dir(DirEntry),
+}
+
+impl<'de> Deserialize<'de> for ManifestEntry {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        struct ManifestEntryVisitor;
+
+        impl<'de> serde::de::Visitor<'de> for ManifestEntryVisitor {
+            type Value = ManifestEntry;
+
+            fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+                formatter.write_str(
+                    "a string, an object with 'in'/'out' keys, or an object with 'in_dir'/'out' keys",
+                )
+            }
+
+            fn visit_str<E>(self, value: &str) -> Result<ManifestEntry, E>
+            where
+                E: serde::de::Error,
+            {
+                Ok(ManifestEntry::File(FileEntry {
+                    r#in: value.to_string(),
+                    out: None,
+                }))
+            }
+
+            fn visit_map<M>(self, mut map: M) -> Result<ManifestEntry, M::Error>
+            where
+                M: serde::de::MapAccess<'de>,
+            {
+                let mut r#in: Option<String> = None;
+                let mut in_dir: Option<String> = None;
+                let mut out: Option<String> = None;
+                let mut exclude: Vec<String> = Vec::new();
+
+                while let Some(key) = map.next_key::<String>()? {
+                    match key.as_str() {
+                        "in" => {
+                            if r#in.is_some() {
+                                return Err(serde::de::Error::duplicate_field("in"));
+                            }
+                            r#in = Some(map.next_value()?);
+                        }
+                        "in_dir" => {
+                            if in_dir.is_some() {
+                                return Err(serde::de::Error::duplicate_field("in_dir"));
+                            }
+                            in_dir = Some(map.next_value()?);
+                        }
+                        "out" => {
+                            if out.is_some() {
+                                return Err(serde::de::Error::duplicate_field("out"));
+                            }
+                            out = Some(map.next_value()?);
+                        }
+                        "exclude" => {
+                            exclude = map.next_value()?;
+                        }
+                        _ => {
+                            let _ = map.next_value::<serde::de::IgnoredAny>()?;
+                        }
+                    }
+                }
+
+                match (r#in, in_dir) {
+                    (Some(_), Some(_)) => Err(serde::de::Error::custom(
+                        "entry cannot have both 'in' and 'in_dir'",
+                    )),
+                    (Some(r#in), None) => Ok(ManifestEntry::File(FileEntry { r#in, out })),
+                    (None, Some(in_dir)) => {
+                        Ok(ManifestEntry::Dir(DirEntry { in_dir, out, exclude }))
+                    }
+                    (None, None) => Err(serde::de::Error::missing_field("in")),
+                }
+            }
+        }
+
+        deserializer.deserialize_any(ManifestEntryVisitor)
+    }
+}
+
+/// Minimal shell-style glob matcher supporting `*` (any run of characters) and `?` (any
+/// single character) — enough for manifest `exclude` patterns without a full glob crate.
+fn glob_match(pattern: &str, text: &str) -> bool {
+    fn matches(pattern: &[char], text: &[char]) -> bool {
+        match pattern.first() {
+            None => text.is_empty(),
+            Some('*') => {
+                matches(&pattern[1..], text) || (!text.is_empty() && matches(pattern, &text[1..]))
+            }
+            Some('?') => !text.is_empty() && matches(&pattern[1..], &text[1..]),
+            Some(c) => text.first() == Some(c) && matches(&pattern[1..], &text[1..]),
+        }
+    }
+
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+    matches(&pattern, &text)
+}
+
+/// Recursively walks `entry.in_dir` (relative to `base`) and mirrors its structure onto
+/// `entry.out`, expanding it into the same flat `FileEntry` list a hand-enumerated
+/// manifest would produce. Entries are visited in sorted order so the resulting image
+/// is deterministic across platforms.
+fn collect_dir_entries(base: &Path, entry: &DirEntry) -> Result<Vec<FileEntry>, MkfatError> {
+    fn walk(
+        source_root: &Path,
+        rel_dir: &Path,
+        entry: &DirEntry,
+        files: &mut Vec<FileEntry>,
+    ) -> Result<(), MkfatError> {
+        let dir_path = source_root.join(rel_dir);
+        let mut children: Vec<_> = fs::read_dir(&dir_path)
+            .map_err(|e| MkfatError::ReadDir { path: dir_path.clone(), source: e })?
+            .collect::<std::io::Result<Vec<_>>>()
+            .map_err(|e| MkfatError::ReadDir { path: dir_path.clone(), source: e })?;
+        children.sort_by_key(|c| c.file_name());
+
+        for child in children {
+            let child_rel = rel_dir.join(child.file_name());
+            let child_rel_str = child_rel.to_string_lossy().replace('\\', "/");
+            if entry.exclude.iter().any(|pat| glob_match(pat, &child_rel_str)) {
+                continue;
+            }
+
+            let file_type = child
+                .file_type()
+                .map_err(|e| MkfatError::Stat { path: child.path(), source: e })?;
+            if file_type.is_dir() {
+                walk(source_root, &child_rel, entry, files)?;
+            } else {
+                files.push(FileEntry {
+                    r#in: format!("{}/{}", entry.get_in_dir(), child_rel_str),
+                    out: Some(format!("{}/{}", entry.get_out(), child_rel_str)),
+                });
+            }
+        }
+
+        Ok(())
+    }
+
+    let source_root = base.join(entry.get_in_dir());
+    let mut files = Vec::new();
+    walk(&source_root, Path::new(""), entry, &mut files)?;
+    Ok(files)
+}
+
+/// Computes the smallest image size (in MB, rounded up) that can hold every manifest
+/// entry at `bytes_per_cluster`, including a heuristic allowance for directory-entry
+/// and FAT-table overhead that isn't known exactly until the volume is formatted. This
+/// avoids wasting space, or overflowing a fixed `--size-mb`, for manifests whose total
+/// size isn't known ahead of time.
+///
+/// `front_offset_bytes` accounts for space the image reserves before the FAT volume
+/// itself starts (the MBR/partition offset), which is invisible to `files` but still has to fit inside `--size-mb`.
+fn auto_size_mb(
+    files: &[FileEntry],
+    base: &Path,
+    bytes_per_cluster: u64,
+    front_offset_bytes: u64,
+) -> Result<u64, MkfatError> {
+    let mut data_bytes: u64 = 0;
+    for entry in files {
+        let full_path = base.join(entry.get_in());
+        let len = fs::metadata(&full_path)
+            .map(|m| m.len())
+            .map_err(|e| MkfatError::Stat { path: full_path.clone(), source: e })?;
+        data_bytes += len.max(1).div_ceil(bytes_per_cluster) * bytes_per_cluster;
+    }
+
+    // Directory entries (a 32-byte slot per LFN chunk of ~13 characters, plus the short
+    // entry) and the FAT table (a few bytes per cluster) are overhead we can't size
+    // exactly without formatting first, so pad generously rather than risk overflow.
+    let entry_overhead = files.len() as u64 * 1024;
+    let fat_overhead = data_bytes / 64 + bytes_per_cluster;
+    let total_bytes = front_offset_bytes + data_bytes + entry_overhead + fat_overhead;
+
+    Ok(total_bytes.div_ceil(1024 * 1024).max(1))
+}
+
+/// Characters VFAT/LFN forbids in any path component.
+const VFAT_RESERVED_CHARS: &[char] = &['"', '*', '/', ':', '<', '>', '?', '\\', '|'];
+
+/// Why a path component can't be written to the image as-is.
+#[derive(Debug)]
+enum NameIssue {
+    TooLong,
+    ReservedChar(char),
+    ControlChar,
+    TrailingDotOrSpace,
+}
+
+impl std::fmt::Display for NameIssue {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            NameIssue::TooLong => write!(f, "exceeds 255 UCS-2 code units"),
+            NameIssue::ReservedChar(c) => write!(f, "contains reserved character '{}'", c),
+            NameIssue::ControlChar => write!(f, "contains a control character"),
+            NameIssue::TrailingDotOrSpace => write!(f, "ends with a '.' or ' '"),
+        }
+    }
+}
+
+/// Checks a single path component against the VFAT long-file-name constraints.
+fn validate_component(name: &str) -> Result<(), NameIssue> {
+    if name.encode_utf16().count() > 255 {
+        return Err(NameIssue::TooLong);
+    }
+    if let Some(c) = name.chars().find(|c| VFAT_RESERVED_CHARS.contains(c)) {
+        return Err(NameIssue::ReservedChar(c));
+    }
+    if name.chars().any(|c| (c as u32) < 0x20) {
+        return Err(NameIssue::ControlChar);
+    }
+    if name.ends_with('.') || name.ends_with(' ') {
+        return Err(NameIssue::TrailingDotOrSpace);
+    }
+    Ok(())
+}
+
+/// Deterministically rewrites a component so it satisfies the VFAT/LFN constraints:
+/// reserved and control characters become `_`, trailing dots/spaces are trimmed, and
+/// the result is truncated by UTF-16 code unit (not byte) to 255 units.
+fn sanitize_component(name: &str) -> String {
+    let mut cleaned: String = name
+        .chars()
+        .map(|c| if VFAT_RESERVED_CHARS.contains(&c) || (c as u32) < 0x20 { '_' } else { c })
+        .collect();
+
+    if cleaned.encode_utf16().count() > 255 {
+        let truncated: Vec<u16> = cleaned.encode_utf16().take(255).collect();
+        cleaned = String::from_utf16_lossy(&truncated);
+    }
+
+    while cleaned.ends_with('.') || cleaned.ends_with(' ') {
+        cleaned.pop();
+    }
+
+    if cleaned.is_empty() {
+        cleaned.push('_');
+    }
+
+    cleaned
+}
+
+/// Derives an *approximate* 8.3 short name for an LFN entry, used only to warn about
+/// same-directory collisions. This is not the numeric-tail (`~1`, `~2`, ...) name
+/// `fatfs` actually assigns on collision — `fatfs` disambiguates those itself, so a
+/// match here is merely a heads-up, never a reason to fail or rewrite a valid manifest.
+fn short_name_for(name: &str) -> String {
+    let (stem, ext) = match name.rsplit_once('.') {
+        Some((s, e)) if !s.is_empty() => (s, e),
+        _ => (name, ""),
+    };
+
+    let clean = |s: &str, max: usize| -> String {
+        s.chars()
+            .filter(|c| !c.is_whitespace() && *c != '.')
+            .map(|c| c.to_ascii_uppercase())
+            .take(max)
+            .collect()
+    };
+
+    let stem_clean = clean(stem, 8);
+    let ext_clean = clean(ext, 3);
+
+    if ext_clean.is_empty() {
+        stem_clean
+    } else {
+        format!("{}.{}", stem_clean, ext_clean)
+    }
+}
+
+/// What to do with one entry after pre-flight name validation.
+enum ValidationOutcome {
+    Write(String),
+    Skip,
+}
+
+/// Validates (and, depending on `policy`, sanitizes) every entry's output path against
+/// VFAT/LFN constraints before any bytes are written, so problems surface as one
+/// actionable error/report instead of a `fatfs::create_file` failure deep inside the
+/// last file of the build. Approximate 8.3 short-name collisions are reported as a
+/// heads-up only (`fatfs` disambiguates them itself with a numeric tail) and never
+/// block, skip, or rewrite an otherwise-valid entry.
+fn validate_output_paths(
+    files: &[FileEntry],
+    policy: OnInvalidPolicy,
+    verbose: bool,
+) -> Result<Vec<ValidationOutcome>, MkfatError> {
+    let mut short_names_by_dir: std::collections::HashMap<String, std::collections::HashSet<String>> =
+        std::collections::HashMap::new();
+    let mut outcomes = Vec::with_capacity(files.len());
+
+    for entry in files {
+        let output_path = entry.get_out();
+        let mut components: Vec<String> = output_path
+            .split('/')
+            .filter(|c| !c.is_empty())
+            .map(str::to_string)
+            .collect();
+
+        let mut issue: Option<(String, NameIssue)> = None;
+        for component in &mut components {
+            if let Err(e) = validate_component(component) {
+                issue.get_or_insert((component.clone(), e));
+                if matches!(policy, OnInvalidPolicy::Sanitize) {
+                    *component = sanitize_component(component);
+                }
+            }
+        }
+
+        let outcome = match issue {
+            None => ValidationOutcome::Write(output_path.to_string()),
+            Some((bad_component, reason)) => match policy {
+                OnInvalidPolicy::Error => {
+                    return Err(MkfatError::InvalidName {
+                        path: output_path.to_string(),
+                        component: bad_component,
+                        reason,
+                    });
+                }
+                OnInvalidPolicy::Skip => {
+                    if verbose {
+                        println!(
+                            "Skipping '{}': invalid name '{}' ({})",
+                            output_path, bad_component, reason
+                        );
+                    }
+                    ValidationOutcome::Skip
+                }
+                OnInvalidPolicy::Sanitize => {
+                    let rewritten_path = components.join("/");
+                    if verbose {
+                        println!(
+                            "Sanitizing '{}' -> '{}' ({})",
+                            output_path, rewritten_path, reason
+                        );
+                    }
+                    ValidationOutcome::Write(rewritten_path)
+                }
+            },
+        };
+
+        // Only entries that will actually be written occupy a short-name slot; a
+        // skipped entry must not be able to "collide" with, and so warn about, one
+        // that's kept.
+        if let ValidationOutcome::Write(ref written_path) = outcome {
+            let dir_key = match written_path.rsplit_once('/') {
+                Some((dir, _)) => dir.to_string(),
+                None => String::new(),
+            };
+            let file_component = written_path.rsplit('/').next().unwrap_or(written_path);
+            let short_name = short_name_for(file_component);
+            let seen = short_names_by_dir.entry(dir_key).or_default();
+            if !seen.insert(short_name) && verbose {
+                println!(
+                    "Note: '{}' has an 8.3 short name similar to another entry in the same \
+                     directory; fatfs will disambiguate it automatically",
+                    written_path
+                );
+            }
+        }
+
+        outcomes.push(outcome);
+    }
+
+    Ok(outcomes)
+}
+
 #[derive(Debug, Deserialize, clap::ValueEnum, Copy, Clone, PartialEq, Eq)]
 #[serde(rename_all = "UPPERCASE")]
 #[value(rename_all = "UPPERCASE")]
@@ -141,8 +816,10 @@ enum ManifestVariant {
 
 #[derive(Debug, Deserialize)]
 struct BuildArgs {
-    files: Vec<FileEntry>,
+    files: Vec<ManifestEntry>,
     variant: Option<ManifestVariant>,
+    bytes_per_sector: Option<u16>,
+    bytes_per_cluster: Option<u32>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -152,6 +829,100 @@ struct Manifest {
     /// Optional output filename; when present and CLI --output not provided,
     /// the effective output path will be base directory joined with this filename
     out: Option<String>,
+    /// Presence of this object (even empty) opts into MBR-partitioned output,
+    /// equivalent to passing `--partitioned` on the CLI.
+    partition: Option<PartitionConfig>,
+}
+
+#[derive(Debug, Deserialize, Default)]
+struct PartitionConfig {
+    /// Starting LBA of the partition; defaults to 2048 (1 MiB alignment) when unset.
+    start_lba: Option<u32>,
+}
+
+/// Unix time of 1980-01-01T00:00:00Z, the earliest date the FAT on-disk timestamp
+/// format can represent.
+const FAT_EPOCH_UNIX_SECS: i64 = 315532800;
+
+/// Splits a day count since the Unix epoch (1970-01-01) into a proleptic-Gregorian
+/// `(year, month, day)` triple. Based on Howard Hinnant's `civil_from_days` algorithm.
+fn civil_from_days(days: i64) -> (u16, u16, u16) {
+    let z = days + 719468;
+    let era = if z >= 0 { z } else { z - 146096 } / 146097;
+    let doe = (z - era * 146097) as u64;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let y = yoe as i64 + era * 400;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let d = doy - (153 * mp + 2) / 5 + 1;
+    let m = if mp < 10 { mp + 3 } else { mp - 9 };
+    let y = if m <= 2 { y + 1 } else { y };
+    (y as u16, m as u16, d as u16)
+}
+
+/// Converts Unix seconds into a `fatfs::DateTime`, truncating to whole seconds (FAT's
+/// finest representable resolution outside the creation-time tenths field).
+fn fat_datetime_from_unix(epoch_secs: i64) -> fatfs::DateTime {
+    let days = epoch_secs.div_euclid(86400);
+    let secs_of_day = epoch_secs.rem_euclid(86400);
+    let (year, month, day) = civil_from_days(days);
+    let hour = (secs_of_day / 3600) as u16;
+    let min = ((secs_of_day % 3600) / 60) as u16;
+    let sec = (secs_of_day % 60) as u16;
+    fatfs::DateTime {
+        date: fatfs::Date { year, month, day },
+        time: fatfs::Time { hour, min, sec, millis: 0 },
+    }
+}
+
+fn unix_secs_now() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or(0)
+}
+
+/// A `fatfs::TimeProvider` that stamps every create with a fixed timestamp when one is
+/// supplied (`--timestamp` or `SOURCE_DATE_EPOCH`), so repeated builds of the same
+/// manifest produce byte-identical images. Falls back to the host wall clock otherwise,
+/// matching `fatfs`'s own default provider.
+///
+/// `fatfs` only ever calls `TimeProvider::get_current_date[_time]` at the moment it
+/// stamps a directory entry (on create, and again on every write), so per-file
+/// timestamps (e.g. a source file's own mtime) have to be staged into `pending` right
+/// before the call that should observe them — the deprecated `File::set_created` and
+/// friends are exactly the `&mut` API this interior-mutable provider replaces.
+#[derive(Debug)]
+struct MkfatTimeProvider {
+    fixed: Option<fatfs::DateTime>,
+    pending: std::cell::Cell<Option<fatfs::DateTime>>,
+}
+
+impl MkfatTimeProvider {
+    fn new(fixed: Option<fatfs::DateTime>) -> Self {
+        Self { fixed, pending: std::cell::Cell::new(None) }
+    }
+
+    /// Stages the timestamp the next create/write should be stamped with; pass `None`
+    /// to fall back to `fixed` (or the wall clock). Stays in effect until overwritten,
+    /// so both the creation stamp and the post-write "modified" stamp see the same
+    /// value for a single file.
+    fn stage(&self, dt: Option<fatfs::DateTime>) {
+        self.pending.set(dt);
+    }
+}
+
+impl fatfs::TimeProvider for MkfatTimeProvider {
+    fn get_current_date(&self) -> fatfs::Date {
+        self.get_current_date_time().date
+    }
+
+    fn get_current_date_time(&self) -> fatfs::DateTime {
+        self.pending
+            .get()
+            .or(self.fixed)
+            .unwrap_or_else(|| fat_datetime_from_unix(unix_secs_now()))
+    }
 }
 
 fn generate_fat_image(
@@ -159,8 +930,53 @@ fn generate_fat_image(
     manifest: &Manifest,
     base: &Path,
     effective_fat_type: FatType,
+    explicit_fat_type: Option<FatType>,
+    effective_timestamp: Option<i64>,
     output_path: &Path,
-) -> Result<(), String> {
+) -> Result<(), MkfatError> {
+    // Expand any `in_dir` tree entries into plain files up front: both auto-sizing and
+    // the write loop further down need the final flat file list.
+    let mut expanded_files: Vec<FileEntry> = Vec::new();
+    for entry in &manifest.build_args.files {
+        match entry {
+            ManifestEntry::File(file_entry) => expanded_files.push(FileEntry {
+                r#in: file_entry.get_in().to_string(),
+                out: Some(file_entry.get_out().to_string()),
+            }),
+            ManifestEntry::Dir(dir_entry) => {
+                expanded_files.extend(collect_dir_entries(base, dir_entry)?)
+            }
+        }
+    }
+
+    let validated = validate_output_paths(&expanded_files, cli.on_invalid, cli.verbose)?;
+
+    let bytes_per_cluster = cli.bytes_per_cluster.or(manifest.build_args.bytes_per_cluster);
+
+    // A manifest `partition` object opts in just like `--partitioned` does.
+    let partitioned = cli.partitioned || manifest.partition.is_some();
+    let partition_start_lba = partitioned.then(|| {
+        cli.partition_start_lba
+            .or_else(|| manifest.partition.as_ref().and_then(|p| p.start_lba))
+            .unwrap_or(DEFAULT_PARTITION_START_LBA)
+    });
+    let bytes_per_sector = cli.bytes_per_sector.or(manifest.build_args.bytes_per_sector);
+
+    // Bytes the image consumes before the FAT volume itself starts: the MBR/partition
+    // offset. `--size-mb auto` has to size around this or a partitioned image ends up
+    // too small to hold its data.
+    let front_offset_bytes = partition_start_lba.unwrap_or(0) as u64 * SECTOR_SIZE;
+
+    let effective_size_mb = match &cli.size_mb {
+        SizeArg::Mb(mb) => *mb,
+        SizeArg::Auto => auto_size_mb(
+            &expanded_files,
+            base,
+            bytes_per_cluster.unwrap_or(4096) as u64,
+            front_offset_bytes,
+        )?,
+    };
+
     // Create and preallocate output file
     let img_file = OpenOptions::new()
         .read(true)
@@ -168,16 +984,10 @@ fn generate_fat_image(
         .create(true)
         .truncate(true)
         .open(output_path)
-        .map_err(|e| {
-            format!(
-                "Failed to open output file '{}': {}",
-                output_path.display(),
-                e
-            )
-        })?;
+        .map_err(|e| MkfatError::OpenOutput { path: output_path.to_path_buf(), source: e })?;
     img_file
-        .set_len(cli.size_mb * 1024 * 1024)
-        .map_err(|e| format!("Failed to set image size: {}", e))?;
+        .set_len(effective_size_mb * 1024 * 1024)
+        .map_err(MkfatError::SetImageSize)?;
 
     // Keep the file in a box to satisfy the 'static lifetime requirement
     let mut boxed_file: Box<dyn ReadWriteSeek> = Box::new(img_file);
@@ -188,86 +998,240 @@ fn generate_fat_image(
         FatType::Fat32 => fatfs::FatType::Fat32,
     };
 
+    let total_sectors = (effective_size_mb * 1024 * 1024 / SECTOR_SIZE) as u32;
+    if let Some(start_lba) = partition_start_lba {
+        write_mbr(&mut boxed_file, effective_fat_type, start_lba, total_sectors)?;
+    }
+
+    // Everything from here on talks to `fs_io`, which is either the raw image file or
+    // the same file seen through a byte range limited to its partition. `format_volume`
+    // seeks to `SeekFrom::End` to size the volume when `total_sectors` isn't set, so the
+    // wrapper has to support full `Seek`, not just `Start` — `StreamSlice` is `fatfs`'s
+    // own suggested way to format/write into a sub-range of a larger disk image.
+    let image_bytes = effective_size_mb * 1024 * 1024;
+    let mut fs_io: Box<dyn ReadWriteSeek> = match partition_start_lba {
+        Some(start_lba) => partition_stream_slice(boxed_file, start_lba, image_bytes)?,
+        None => boxed_file,
+    };
+
     // Format the volume
     let mut label_bytes = [b' '; 11];
     label_bytes[..cli.label.len()].copy_from_slice(cli.label.as_bytes());
-    let format_options = fatfs::FormatVolumeOptions::new()
+    let mut format_options = fatfs::FormatVolumeOptions::new()
         .volume_label(label_bytes)
         .fat_type(fat_type);
-    fatfs::format_volume(&mut boxed_file, format_options)
-        .map_err(|e| format!("Failed to format volume: {}", e))?;
+    // Only override fatfs's size-adaptive cluster default when the user/manifest asked
+    // for a specific one; forcing 4096 unconditionally can make a large FAT16 image
+    // exceed the 65525-cluster limit where fatfs would have picked a bigger cluster.
+    if let Some(bytes_per_cluster) = bytes_per_cluster {
+        format_options = format_options.bytes_per_cluster(bytes_per_cluster);
+    }
+    if let Some(bytes_per_sector) = bytes_per_sector {
+        format_options = format_options.bytes_per_sector(bytes_per_sector);
+    }
+    if let Some(start_lba) = partition_start_lba {
+        // `StreamSlice` correctly bounds `SeekFrom::End` to the partition's own range,
+        // so `format_volume`'s size-discovery probe would work either way here; spelling
+        // the sector count out explicitly just avoids relying on that probe at all.
+        // The count is in the volume's own sector size, which may differ from the MBR's
+        // 512-byte sectors.
+        let partition_bytes = total_sectors.saturating_sub(start_lba) as u64 * SECTOR_SIZE;
+        let volume_sector_size = bytes_per_sector.unwrap_or(SECTOR_SIZE as u16) as u64;
+        format_options =
+            format_options.total_sectors((partition_bytes / volume_sector_size) as u32);
+    }
+    fatfs::format_volume(&mut fs_io, format_options)
+        .map_err(|e| MkfatError::Format(fatfs_io_error(e)))?;
+
+    // Rewind for filesystem operations (relative to the partition start, if any)
+    fs_io.seek(SeekFrom::Start(0)).map_err(MkfatError::Seek)?;
 
-    // Rewind the file for filesystem operations
-    boxed_file
-        .seek(SeekFrom::Start(0))
-        .map_err(|e| format!("Failed to seek in image file: {}", e))?;
+    let fixed_date_time = effective_timestamp.map(fat_datetime_from_unix);
+    // `FsOptions::time_provider` takes a `&'static` reference, and we need that same
+    // handle afterwards to stage per-file timestamps, so leak it once up front.
+    let time_provider: &'static MkfatTimeProvider = Box::leak(Box::new(MkfatTimeProvider::new(fixed_date_time)));
 
     // Create filesystem
-    let fs = FileSystem::new(boxed_file, FsOptions::new())
-        .map_err(|e| format!("Failed to create filesystem: {}", e))?;
+    let fs = FileSystem::new(fs_io, FsOptions::new().time_provider(time_provider))
+        .map_err(|e| MkfatError::CreateFilesystem(fatfs_io_error(e)))?;
     let root_dir = fs.root_dir();
 
+    // Creates (or opens, if it already exists) each path component as a directory.
+    // Directories always get `fixed_date_time` when reproducible builds are
+    // requested, or the wall clock otherwise — never a per-file staged timestamp —
+    // so clear any staging left over from a preceding file.
+    let mkdir_p = |components: &[Component]| {
+        time_provider.stage(None);
+        let mut dir = root_dir.clone();
+        for comp in components {
+            if let Component::RootDir = comp {
+                continue;
+            }
+            let name = comp
+                .as_os_str()
+                .to_str()
+                .ok_or_else(|| MkfatError::InvalidPath("invalid UTF-8 in path".to_string()))?;
+            dir = match dir.create_dir(name) {
+                Ok(created) => created,
+                Err(_) => dir.open_dir(name).map_err(|e| MkfatError::CreateDir {
+                    path: name.to_string(),
+                    source: fatfs_io_error(e),
+                })?,
+            };
+        }
+        Ok(dir)
+    };
+
     if let Some(directories) = &manifest.directories {
         for dir_path in directories {
             if cli.verbose {
                 println!("Creating directory: {}", dir_path);
             }
             let components_vec: Vec<_> = Path::new(dir_path).components().collect();
-            let mut dir = root_dir.clone();
-            for comp in &components_vec {
-                if let Component::RootDir = comp {
-                    continue;
-                }
-                let name = comp.as_os_str().to_str().ok_or("Invalid UTF-8 in path")?;
-                dir = dir
-                    .create_dir(name)
-                    .or_else(|_| dir.open_dir(name))
-                    .map_err(|e| format!("Failed to create directory '{}': {}", name, e))?;
-            }
+            mkdir_p(&components_vec)?;
         }
     }
 
-    for entry in manifest.build_args.files.iter() {
+    let mut written_files: Vec<(String, u64)> = Vec::new();
+
+    for (entry, outcome) in expanded_files.iter().zip(validated) {
         let input_path = entry.get_in();
-        let output_path = entry.get_out();
+        let output_path = match outcome {
+            ValidationOutcome::Write(path) => path,
+            ValidationOutcome::Skip => continue,
+        };
+        let output_path = output_path.as_str();
 
         if cli.verbose {
             println!("Adding file: {} -> {}", input_path, output_path);
         }
 
         let full_input_path = base.join(input_path);
-        let file_data = fs::read(&full_input_path).map_err(|e| {
-            format!(
-                "Failed to read input file '{}': {}",
-                full_input_path.display(),
-                e
-            )
-        })?;
+        let file_data = fs::read(&full_input_path)
+            .map_err(|e| MkfatError::ReadInput { path: full_input_path.clone(), source: e })?;
 
-        let components_vec: Vec<_> = Path::new(output_path).components().collect();
-        let mut dir = root_dir.clone();
+        // When no fixed timestamp was requested, stamp the written entry with the
+        // source file's own mtime so the image reflects its inputs, not build time.
+        let source_date_time = match fixed_date_time {
+            Some(dt) => dt,
+            None => fs::metadata(&full_input_path)
+                .and_then(|m| m.modified())
+                .ok()
+                .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+                .map(|d| fat_datetime_from_unix(d.as_secs() as i64))
+                .unwrap_or_else(|| fat_datetime_from_unix(unix_secs_now())),
+        };
 
-        for comp in &components_vec[..components_vec.len().saturating_sub(1)] {
-            if let Component::RootDir = comp {
-                continue;
-            }
-            let name = comp.as_os_str().to_str().ok_or("Invalid UTF-8 in path")?;
-            dir = dir
-                .create_dir(name)
-                .or_else(|_| dir.open_dir(name))
-                .map_err(|e| format!("Failed to create directory '{}': {}", name, e))?;
-        }
+        let components_vec: Vec<_> = Path::new(output_path).components().collect();
+        let dir = mkdir_p(&components_vec[..components_vec.len().saturating_sub(1)])?;
 
         let file_name = Path::new(output_path)
             .file_name()
             .and_then(|s| s.to_str())
-            .ok_or("Invalid file name")?;
-        let mut fat_file = dir
-            .create_file(file_name)
-            .map_err(|e| format!("Failed to create file '{}': {}", file_name, e))?;
-        fat_file
-            .write_all(&file_data)
-            .map_err(|e| format!("Failed to write to file '{}': {}", file_name, e))?;
+            .ok_or_else(|| MkfatError::InvalidPath(format!("invalid file name in '{}'", output_path)))?;
+        // Stage source_date_time so both the creation stamp and the post-write
+        // "modified" stamp (fatfs re-queries the provider on each) see the same value.
+        time_provider.stage(Some(source_date_time));
+        let mut fat_file = dir.create_file(file_name).map_err(|e| MkfatError::CreateFile {
+            name: file_name.to_string(),
+            source: fatfs_io_error(e),
+        })?;
+        fat_file.write_all(&file_data).map_err(|e| MkfatError::WriteFile {
+            name: file_name.to_string(),
+            source: e,
+        })?;
+        written_files.push((output_path.to_string(), file_data.len() as u64));
+    }
+
+    // Drop the filesystem (and the file handle it owns) before possibly reopening the
+    // image for verification below; `root_dir` borrows from `fs`, so it has to go first.
+    drop(root_dir);
+    drop(fs);
+
+    if cli.verify {
+        verify_image(cli, output_path, partition_start_lba, explicit_fat_type, &written_files)?;
+    }
+
+    Ok(())
+}
+
+/// Reopens the freshly-written image and reports its actual on-disk geometry: FAT
+/// type, cluster size, and total/used/free space, plus each written file's size.
+///
+/// `FormatVolumeOptions::fat_type` is only a hint `fatfs` feeds into its cluster-size
+/// heuristics — the type actually written still depends on the resulting cluster count,
+/// so a size that's too small (or large) for the requested variant silently formats as
+/// something else. `requested_fat_type` is `None` when the caller never explicitly
+/// asked for a variant (we just defaulted to FAT32), in which case whatever `fatfs`
+/// picked is fine and there's nothing to fail on.
+fn verify_image(
+    cli: &Cli,
+    output_path: &Path,
+    partition_start_lba: Option<u32>,
+    requested_fat_type: Option<FatType>,
+    written_files: &[(String, u64)],
+) -> Result<(), MkfatError> {
+    let img_file = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open(output_path)
+        .map_err(|e| MkfatError::Reopen { path: output_path.to_path_buf(), source: e })?;
+    let image_bytes = img_file
+        .metadata()
+        .map_err(|e| MkfatError::Reopen { path: output_path.to_path_buf(), source: e })?
+        .len();
+
+    let boxed_file: Box<dyn ReadWriteSeek> = Box::new(img_file);
+    let mut fs_io: Box<dyn ReadWriteSeek> = match partition_start_lba {
+        Some(start_lba) => partition_stream_slice(boxed_file, start_lba, image_bytes)?,
+        None => boxed_file,
+    };
+    fs_io.seek(SeekFrom::Start(0)).map_err(MkfatError::Seek)?;
+
+    let fs = FileSystem::new(fs_io, FsOptions::new())
+        .map_err(|e| MkfatError::CreateFilesystem(fatfs_io_error(e)))?;
+
+    let actual_fat_type = fs.fat_type();
+    if let Some(requested_fat_type) = requested_fat_type {
+        let expected_fat_type = match requested_fat_type {
+            FatType::Fat12 => fatfs::FatType::Fat12,
+            FatType::Fat16 => fatfs::FatType::Fat16,
+            FatType::Fat32 => fatfs::FatType::Fat32,
+        };
+        if actual_fat_type != expected_fat_type {
+            return Err(MkfatError::VariantMismatch {
+                requested: expected_fat_type,
+                actual: actual_fat_type,
+            });
+        }
+    }
+
+    let stats = fs.stats().map_err(|e| MkfatError::Stats(fatfs_io_error(e)))?;
+    let cluster_size = stats.cluster_size() as u64;
+    let total_bytes = stats.total_clusters() as u64 * cluster_size;
+    let free_bytes = stats.free_clusters() as u64 * cluster_size;
+    let used_bytes = total_bytes.saturating_sub(free_bytes);
+
+    if !cli.quiet {
+        println!(
+            "Verified: {:?}, cluster size {} bytes, {} used / {} free / {} total bytes",
+            actual_fat_type, cluster_size, used_bytes, free_bytes, total_bytes
+        );
+    }
+
+    if !cli.quiet {
+        let report = serde_json::json!({
+            "fs_type": format!("{:?}", actual_fat_type),
+            "cluster_size": cluster_size,
+            "total_bytes": total_bytes,
+            "used_bytes": used_bytes,
+            "free_bytes": free_bytes,
+            "files": written_files
+                .iter()
+                .map(|(path, size)| serde_json::json!({"path": path, "bytes": size}))
+                .collect::<Vec<_>>(),
+        });
+        println!("{}", report);
     }
 
     Ok(())
@@ -276,16 +1240,21 @@ fn generate_fat_image(
 fn main() {
     if let Err(e) = run() {
         eprintln!("Error: {}", e);
+        let mut source = std::error::Error::source(&e);
+        while let Some(cause) = source {
+            eprintln!("Caused by: {}", cause);
+            source = cause.source();
+        }
         std::process::exit(1);
     }
 }
 
-fn run() -> Result<(), String> {
+fn run() -> Result<(), MkfatError> {
     let mut cli = Cli::parse();
 
     if cli.base.is_relative() {
         cli.base = std::env::current_dir()
-            .map_err(|e| format!("Failed to get current directory: {}", e))?
+            .map_err(MkfatError::CurrentDir)?
             .join(&cli.base);
     }
 
@@ -293,13 +1262,8 @@ fn run() -> Result<(), String> {
         if !cli.quiet {
             println!("Reading manifest: {}", manifest_path.display());
         }
-        fs::read_to_string(manifest_path).map_err(|e| {
-            format!(
-                "Failed to read manifest file '{}': {}",
-                manifest_path.display(),
-                e
-            )
-        })?
+        fs::read_to_string(manifest_path)
+            .map_err(|e| MkfatError::ReadManifest { path: manifest_path.clone(), source: e })?
     } else {
         if !cli.quiet {
             println!("Reading manifest from stdin");
@@ -307,28 +1271,26 @@ fn run() -> Result<(), String> {
         let mut buffer = String::new();
         std::io::stdin()
             .read_to_string(&mut buffer)
-            .map_err(|e| format!("Failed to read from stdin: {}", e))?;
+            .map_err(MkfatError::ReadManifestStdin)?;
         buffer
     };
-    let manifest: Manifest = serde_json::from_str(&json_str)
-        .map_err(|e| format!("Failed to parse manifest file: {}", e))?;
-
-    // Determine effective FAT type: CLI overrides manifest, else default to FAT32
-    let effective_fat_type = if let Some(cli_variant) = cli.variant {
-        match cli_variant {
-            ManifestVariant::FAT12 => FatType::Fat12,
-            ManifestVariant::FAT16 => FatType::Fat16,
-            ManifestVariant::FAT32 => FatType::Fat32,
-        }
-    } else if let Some(variant) = &manifest.build_args.variant {
-        match variant {
-            ManifestVariant::FAT12 => FatType::Fat12,
-            ManifestVariant::FAT16 => FatType::Fat16,
-            ManifestVariant::FAT32 => FatType::Fat32,
-        }
+    let manifest: Manifest =
+        serde_json::from_str(&json_str).map_err(MkfatError::ParseManifest)?;
+
+    // Determine effective FAT type: CLI overrides manifest, else default to FAT32.
+    // `explicit_fat_type` is `None` when neither was given, so `--verify` knows the
+    // difference between "the caller asked for this variant" and "we just defaulted".
+    let explicit_fat_type = if let Some(cli_variant) = cli.variant {
+        Some(cli_variant)
     } else {
-        FatType::Fat32
-    };
+        manifest.build_args.variant
+    }
+    .map(|variant| match variant {
+        ManifestVariant::FAT12 => FatType::Fat12,
+        ManifestVariant::FAT16 => FatType::Fat16,
+        ManifestVariant::FAT32 => FatType::Fat32,
+    });
+    let effective_fat_type = explicit_fat_type.unwrap_or(FatType::Fat32);
 
     // Determine effective output path: CLI overrides manifest 'out'
     let effective_output_path = if let Some(cli_out) = &cli.output {
@@ -336,9 +1298,25 @@ fn run() -> Result<(), String> {
     } else if let Some(out_name) = &manifest.out {
         cli.base.join(out_name)
     } else {
-        return Err("Output path not specified. Provide --output or 'out' in manifest.".to_string());
+        return Err(MkfatError::MissingOutput);
     };
 
+    // Determine effective build timestamp: CLI overrides SOURCE_DATE_EPOCH; otherwise
+    // each file keeps its own source mtime (see `generate_fat_image`).
+    let effective_timestamp = cli.timestamp.or_else(|| {
+        std::env::var("SOURCE_DATE_EPOCH")
+            .ok()
+            .and_then(|v| v.parse::<i64>().ok())
+    });
+    // FAT timestamps can't represent dates before 1980-01-01 (the format's epoch), so
+    // reject an out-of-range request up front rather than handing fatfs a DateTime it
+    // may clamp or panic on.
+    if let Some(ts) = effective_timestamp {
+        if ts < FAT_EPOCH_UNIX_SECS {
+            return Err(MkfatError::TimestampTooOld(ts));
+        }
+    }
+
     if !cli.quiet {
         println!("Generating FAT image: {}", effective_output_path.display());
         if cli.verbose {
@@ -348,6 +1326,9 @@ fn run() -> Result<(), String> {
                 FatType::Fat32 => "fat32",
             };
             println!("FAT type: {}", fat_type_str);
+            if let Some(ts) = effective_timestamp {
+                println!("Timestamp: {} (unix seconds)", ts);
+            }
         }
     }
 
@@ -356,6 +1337,8 @@ fn run() -> Result<(), String> {
         &manifest,
         &cli.base,
         effective_fat_type,
+        explicit_fat_type,
+        effective_timestamp,
         &effective_output_path,
     )?;
 
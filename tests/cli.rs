@@ -30,7 +30,7 @@ fn test_mkfat_integration() {
         .arg("--manifest")
         .arg(&manifest_path)
         .arg("--base")
-        .arg(&base_path)
+        .arg(base_path)
         .arg("--output")
         .arg(&output_path)
         .arg("--size-mb")
@@ -68,7 +68,7 @@ fn test_mkfat_integration_string_entry() {
         .arg("--manifest")
         .arg(&manifest_path)
         .arg("--base")
-        .arg(&base_path)
+        .arg(base_path)
         .arg("--output")
         .arg(&output_path)
         .arg("--size-mb")
@@ -105,7 +105,7 @@ fn test_mkfat_integration_stdin() {
 
     let mut child = Command::new(env!("CARGO_BIN_EXE_mkfat"))
         .arg("--base")
-        .arg(&base_path)
+        .arg(base_path)
         .arg("--output")
         .arg(&output_path)
         .arg("--size-mb")
@@ -157,7 +157,7 @@ fn test_cli_overrides_manifest_out() {
         .arg("--manifest")
         .arg(&manifest_path)
         .arg("--base")
-        .arg(&base_path)
+        .arg(base_path)
         .arg("--output")
         .arg(&cli_output_path)
         .arg("--size-mb")
@@ -172,6 +172,360 @@ fn test_cli_overrides_manifest_out() {
     assert!(!manifest_output_path.exists());
 }
 
+#[test]
+fn test_timestamp_produces_byte_identical_images() {
+    let temp_dir = tempdir().unwrap();
+    let base_path = temp_dir.path();
+
+    let manifest_path = base_path.join("boot.json");
+    let file_to_include_path = base_path.join("hello.txt");
+    fs::write(&file_to_include_path, "Hello, world!").unwrap();
+
+    let manifest_content = r#"{
+        "build_args": {
+            "files": [
+                {
+                    "in": "hello.txt",
+                    "out": "greeting/hello.txt"
+                }
+            ]
+        },
+        "out": "test.fat"
+    }"#;
+    fs::write(&manifest_path, manifest_content).unwrap();
+
+    let run = |output_path: &std::path::Path| {
+        let status = Command::new(env!("CARGO_BIN_EXE_mkfat"))
+            .arg("--manifest")
+            .arg(&manifest_path)
+            .arg("--base")
+            .arg(base_path)
+            .arg("--output")
+            .arg(output_path)
+            .arg("--size-mb")
+            .arg("16")
+            .arg("--label")
+            .arg("BOOT")
+            .arg("--timestamp")
+            .arg("1000000000")
+            .status()
+            .expect("Failed to execute command");
+        assert!(status.success());
+    };
+
+    let output_a = base_path.join("a.fat");
+    let output_b = base_path.join("b.fat");
+    run(&output_a);
+    run(&output_b);
+
+    let bytes_a = fs::read(&output_a).unwrap();
+    let bytes_b = fs::read(&output_b).unwrap();
+    assert_eq!(bytes_a, bytes_b);
+}
+
+#[test]
+fn test_timestamp_before_fat_epoch_is_rejected() {
+    let temp_dir = tempdir().unwrap();
+    let base_path = temp_dir.path();
+
+    let manifest_path = base_path.join("boot.json");
+    let output_path = base_path.join("test.fat");
+    let file_to_include_path = base_path.join("hello.txt");
+    fs::write(&file_to_include_path, "Hello, world!").unwrap();
+
+    let manifest_content = r#"{
+        "build_args": {
+            "files": ["hello.txt"]
+        },
+        "out": "test.fat"
+    }"#;
+    fs::write(&manifest_path, manifest_content).unwrap();
+
+    let status = Command::new(env!("CARGO_BIN_EXE_mkfat"))
+        .arg("--manifest")
+        .arg(&manifest_path)
+        .arg("--base")
+        .arg(base_path)
+        .arg("--output")
+        .arg(&output_path)
+        .arg("--size-mb")
+        .arg("16")
+        .arg("--timestamp")
+        .arg("0") // 1970-01-01, before the FAT epoch
+        .status()
+        .expect("Failed to execute command");
+
+    assert!(!status.success());
+    assert!(!output_path.exists());
+}
+
+#[test]
+fn test_similar_short_names_do_not_fail_default_validation() {
+    // Two distinct long names that approximate to the same 8.3 short name must still
+    // build under the default `--on-invalid error` policy: fatfs disambiguates
+    // collisions itself, so this isn't a real conflict.
+    let temp_dir = tempdir().unwrap();
+    let base_path = temp_dir.path();
+
+    let manifest_path = base_path.join("boot.json");
+    let output_path = base_path.join("test.fat");
+    fs::write(base_path.join("bootloader-a.efi"), "a").unwrap();
+    fs::write(base_path.join("bootloader-b.efi"), "b").unwrap();
+
+    let manifest_content = r#"{
+        "build_args": {
+            "files": [
+                "bootloader-a.efi",
+                "bootloader-b.efi"
+            ]
+        },
+        "out": "test.fat"
+    }"#;
+    fs::write(&manifest_path, manifest_content).unwrap();
+
+    let status = Command::new(env!("CARGO_BIN_EXE_mkfat"))
+        .arg("--manifest")
+        .arg(&manifest_path)
+        .arg("--base")
+        .arg(base_path)
+        .arg("--output")
+        .arg(&output_path)
+        .arg("--size-mb")
+        .arg("16")
+        .status()
+        .expect("Failed to execute command");
+
+    assert!(status.success());
+    assert!(output_path.exists());
+}
+
+#[test]
+fn test_verify_prints_json_report() {
+    let temp_dir = tempdir().unwrap();
+    let base_path = temp_dir.path();
+
+    let manifest_path = base_path.join("boot.json");
+    let output_path = base_path.join("test.fat");
+    fs::write(base_path.join("hello.txt"), "Hello, world!").unwrap();
+
+    let manifest_content = r#"{
+        "build_args": {
+            "files": ["hello.txt"]
+        },
+        "out": "test.fat"
+    }"#;
+    fs::write(&manifest_path, manifest_content).unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_mkfat"))
+        .arg("--manifest")
+        .arg(&manifest_path)
+        .arg("--base")
+        .arg(base_path)
+        .arg("--output")
+        .arg(&output_path)
+        .arg("--size-mb")
+        .arg("16")
+        .arg("--verify")
+        .output()
+        .expect("Failed to execute command");
+
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("\"fs_type\""));
+}
+
+#[test]
+fn test_verify_quiet_suppresses_json_report() {
+    let temp_dir = tempdir().unwrap();
+    let base_path = temp_dir.path();
+
+    let manifest_path = base_path.join("boot.json");
+    let output_path = base_path.join("test.fat");
+    fs::write(base_path.join("hello.txt"), "Hello, world!").unwrap();
+
+    let manifest_content = r#"{
+        "build_args": {
+            "files": ["hello.txt"]
+        },
+        "out": "test.fat"
+    }"#;
+    fs::write(&manifest_path, manifest_content).unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_mkfat"))
+        .arg("--manifest")
+        .arg(&manifest_path)
+        .arg("--base")
+        .arg(base_path)
+        .arg("--output")
+        .arg(&output_path)
+        .arg("--size-mb")
+        .arg("16")
+        .arg("--verify")
+        .arg("--quiet")
+        .output()
+        .expect("Failed to execute command");
+
+    assert!(output.status.success());
+    assert!(output.stdout.is_empty());
+}
+
+#[test]
+fn test_in_dir_mirrors_directory_tree_and_excludes_globs() {
+    let temp_dir = tempdir().unwrap();
+    let base_path = temp_dir.path();
+
+    let manifest_path = base_path.join("boot.json");
+    let output_path = base_path.join("test.fat");
+
+    let rootfs = base_path.join("rootfs/boot");
+    fs::create_dir_all(rootfs.join("efi")).unwrap();
+    fs::write(rootfs.join("kernel.img"), "kernel bytes").unwrap();
+    fs::write(rootfs.join("efi/bootaa64.efi"), "efi bytes").unwrap();
+    fs::write(rootfs.join("kernel.img.bak"), "stale build artifact").unwrap();
+
+    let manifest_content = r#"{
+        "build_args": {
+            "files": [
+                {
+                    "in_dir": "rootfs/boot",
+                    "out": "BOOT",
+                    "exclude": ["*.bak"]
+                }
+            ]
+        },
+        "out": "test.fat"
+    }"#;
+    fs::write(&manifest_path, manifest_content).unwrap();
+
+    let status = Command::new(env!("CARGO_BIN_EXE_mkfat"))
+        .arg("--manifest")
+        .arg(&manifest_path)
+        .arg("--base")
+        .arg(base_path)
+        .arg("--output")
+        .arg(&output_path)
+        .arg("--size-mb")
+        .arg("16")
+        .status()
+        .expect("Failed to execute command");
+
+    assert!(status.success());
+
+    let image_bytes = fs::read(&output_path).unwrap();
+    let cursor = std::io::Cursor::new(image_bytes);
+    let fs = fatfs::FileSystem::new(cursor, fatfs::FsOptions::new()).expect("failed to mount image");
+    let root = fs.root_dir();
+    let boot_dir = root.open_dir("BOOT").expect("missing BOOT dir");
+
+    let mut kernel = boot_dir.open_file("kernel.img").expect("missing kernel.img");
+    let mut kernel_contents = String::new();
+    std::io::Read::read_to_string(&mut kernel, &mut kernel_contents).expect("failed to read kernel.img");
+    assert_eq!(kernel_contents, "kernel bytes");
+
+    let mut efi = boot_dir.open_dir("efi").expect("missing efi dir").open_file("bootaa64.efi").expect("missing bootaa64.efi");
+    let mut efi_contents = String::new();
+    std::io::Read::read_to_string(&mut efi, &mut efi_contents).expect("failed to read bootaa64.efi");
+    assert_eq!(efi_contents, "efi bytes");
+
+    assert!(boot_dir.open_file("kernel.img.bak").is_err());
+}
+
+#[test]
+fn test_partitioned_image_round_trips_a_file() {
+    // A partitioned image must format and accept files through the MBR offset, then
+    // read back byte-identical via a plain fatfs mount at that same offset.
+    let temp_dir = tempdir().unwrap();
+    let base_path = temp_dir.path();
+
+    let manifest_path = base_path.join("boot.json");
+    let output_path = base_path.join("test.fat");
+    let contents = "Hello from inside a partition!";
+    fs::write(base_path.join("hello.txt"), contents).unwrap();
+
+    let manifest_content = r#"{
+        "build_args": {
+            "files": [
+                {
+                    "in": "hello.txt",
+                    "out": "greeting/hello.txt"
+                }
+            ]
+        },
+        "out": "test.fat"
+    }"#;
+    fs::write(&manifest_path, manifest_content).unwrap();
+
+    let status = Command::new(env!("CARGO_BIN_EXE_mkfat"))
+        .arg("--manifest")
+        .arg(&manifest_path)
+        .arg("--base")
+        .arg(base_path)
+        .arg("--output")
+        .arg(&output_path)
+        .arg("--size-mb")
+        .arg("32")
+        .arg("--label")
+        .arg("BOOT")
+        .arg("--partitioned")
+        .status()
+        .expect("Failed to execute command");
+
+    assert!(status.success());
+
+    // Default partition start is LBA 2048; mount the image from there directly.
+    const SECTOR_SIZE: u64 = 512;
+    const DEFAULT_PARTITION_START_LBA: u64 = 2048;
+    let image_bytes = fs::read(&output_path).unwrap();
+    let partition_bytes = image_bytes[(DEFAULT_PARTITION_START_LBA * SECTOR_SIZE) as usize..].to_vec();
+    let cursor = std::io::Cursor::new(partition_bytes);
+    let fs = fatfs::FileSystem::new(cursor, fatfs::FsOptions::new()).expect("failed to mount partition");
+    let root = fs.root_dir();
+    let mut file = root
+        .open_dir("greeting")
+        .expect("missing greeting dir")
+        .open_file("hello.txt")
+        .expect("missing hello.txt");
+    let mut read_back = String::new();
+    std::io::Read::read_to_string(&mut file, &mut read_back).expect("failed to read file");
+    assert_eq!(read_back, contents);
+}
+
+#[test]
+fn test_auto_size_partitioned_image_fits_data() {
+    // `--size-mb auto` has to account for the MBR/partition-start offset, not just the
+    // data bytes, or a partitioned image comes out too small to hold its files.
+    let temp_dir = tempdir().unwrap();
+    let base_path = temp_dir.path();
+
+    let manifest_path = base_path.join("boot.json");
+    let output_path = base_path.join("test.fat");
+    fs::write(base_path.join("hello.txt"), "Hello, world!").unwrap();
+
+    let manifest_content = r#"{
+        "build_args": {
+            "files": ["hello.txt"]
+        },
+        "out": "test.fat"
+    }"#;
+    fs::write(&manifest_path, manifest_content).unwrap();
+
+    let status = Command::new(env!("CARGO_BIN_EXE_mkfat"))
+        .arg("--manifest")
+        .arg(&manifest_path)
+        .arg("--base")
+        .arg(base_path)
+        .arg("--output")
+        .arg(&output_path)
+        .arg("--size-mb")
+        .arg("auto")
+        .arg("--partitioned")
+        .status()
+        .expect("Failed to execute command");
+
+    assert!(status.success());
+    assert!(output_path.exists());
+}
+
 #[test]
 fn test_cli_overrides_manifest_variant() {
     use std::fs::File;
@@ -201,7 +555,7 @@ fn test_cli_overrides_manifest_variant() {
         .arg("--manifest")
         .arg(&manifest_path)
         .arg("--base")
-        .arg(&base_path)
+        .arg(base_path)
         .arg("--variant")
         .arg("FAT32")
         .arg("--verbose")